@@ -1,16 +1,18 @@
+#[path = "build_system/mod.rs"]
+mod build_system;
+
 use cc;
+#[cfg(target_arch = "x86_64")]
 use core::arch::x86_64::__cpuid_count;
 use rerun_except::rerun_except;
 use std::env;
 use std::fs;
-use std::os::unix::fs as unix_fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
 const FEATURE_CHECKS_PATH: &str = "feature_checks";
 
 const C_DEPS_DIR: &str = "c_deps";
-const C_DEPS_MAKEFILE: &str = "c_deps.mk";
 
 /// Simple feature check, returning `true` if we have the feature.
 ///
@@ -24,79 +26,124 @@ fn feature_check(filename: &str, output_file: &str) -> bool {
     check_build.file(path).try_compile(output_file).is_ok()
 }
 
-fn make_c_deps_dir() -> PathBuf {
-    let out_dir = env::var("OUT_DIR").unwrap();
-    let mut c_deps_dir = PathBuf::from(out_dir);
-    c_deps_dir.push(C_DEPS_DIR);
-
-    if !c_deps_dir.exists() {
-        fs::create_dir(&c_deps_dir).unwrap();
-
-        let mut dest = c_deps_dir.clone();
-        dest.push(C_DEPS_MAKEFILE);
-
-        let mut src = env::current_dir().unwrap();
-        src.push(C_DEPS_MAKEFILE);
-
-        unix_fs::symlink(src, dest).unwrap();
-    }
-
-    c_deps_dir
-}
-
-fn build_libxdc(c_deps_dir: &Path) {
-    eprintln!("Building libxdc...");
-
-    let prev_dir = env::current_dir().unwrap();
-    env::set_current_dir(&c_deps_dir).unwrap();
-    let res = Command::new("make")
-        .arg("-f")
-        .arg(C_DEPS_MAKEFILE)
-        .output()
-        .unwrap_or_else(|_| panic!("Fatal error when building libxdc"));
-    if !res.status.success() {
-        eprintln!("libxdc build failed\n>>> stdout");
-        eprintln!("stdout: {}", String::from_utf8_lossy(&res.stdout));
-        eprintln!("\n>>> stderr");
-        eprintln!("stderr: {}", String::from_utf8_lossy(&res.stderr));
-        panic!();
-    }
+// Builds `dir` with `make`, then `make install`s it into `inst_dir` so that
+// both libxdc and capstone end up under the one `include`/`lib` pair that
+// `c_build` is told to use, regardless of which checkout produced them.
+fn build_with_make(dir: &Path, name: &str, target: &str, host: &str, inst_dir: &Path) {
+    eprintln!("Building {}...", name);
+
+    let cross_env = |cmd: &mut Command| {
+        // Cross-compiling means pointing the Makefile at the target's
+        // compiler instead of whatever `make` would pick up from the host
+        // environment. Resolve it the same way `cc` resolves it for our own
+        // C sources, rather than guessing a `<target>-gcc` binary name (cross
+        // toolchain prefixes often don't match the Rust target triple, e.g.
+        // Debian's `aarch64-linux-gnu-gcc` for `aarch64-unknown-linux-gnu`)
+        // and handing it a Clang-only `--target=` flag that GNU gcc rejects.
+        if target != host {
+            let compiler = cc::Build::new().target(target).host(host).get_compiler();
+            cmd.env("CC", compiler.path());
+            let cflags = compiler
+                .args()
+                .iter()
+                .map(|arg| arg.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" ");
+            cmd.env("CFLAGS", cflags);
+        }
+    };
+
+    let run_make = |args: &[&str]| {
+        let mut cmd = Command::new("make");
+        cmd.current_dir(dir).args(args);
+        cross_env(&mut cmd);
+        let res = cmd
+            .output()
+            .unwrap_or_else(|_| panic!("Fatal error when building {}", name));
+        if !res.status.success() {
+            eprintln!("{} build failed\n>>> stdout", name);
+            eprintln!("stdout: {}", String::from_utf8_lossy(&res.stdout));
+            eprintln!("\n>>> stderr");
+            eprintln!("stderr: {}", String::from_utf8_lossy(&res.stderr));
+            panic!();
+        }
+    };
 
-    env::set_current_dir(&prev_dir).unwrap();
+    run_make(&[]);
+    run_make(&[
+        "install",
+        &format!("PREFIX={}", inst_dir.display()),
+    ]);
 }
 
-// Checks if the CPU supports Intel Processor Trace.
+// Checks if the build host's CPU supports Intel Processor Trace. Used
+// solely to decide whether to emit the `perf_pt_test` cfg, which gates
+// tests that need real PT hardware; see `hwtracer::pt_supported()` for
+// the runtime check callers actually use.
+//
+// build.rs is always compiled for the host triple, so this needs its own
+// `target_arch` guard distinct from the crate's: building on a
+// non-x86_64 host (e.g. cross-compiling to x86_64-linux from an aarch64
+// machine) must not try to compile `__cpuid_count`, which doesn't exist
+// outside `core::arch::x86_64`.
+#[cfg(target_arch = "x86_64")]
 fn cpu_supports_pt() -> bool {
     let res = unsafe { __cpuid_count(0x7, 0x0) };
     (res.ebx & (1 << 25)) != 0
 }
 
+#[cfg(not(target_arch = "x86_64"))]
+fn cpu_supports_pt() -> bool {
+    false
+}
+
 fn main() {
     let mut c_build = cc::Build::new();
 
-    let c_deps_dir = make_c_deps_dir();
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let c_deps_dir = out_dir.join(C_DEPS_DIR);
     let c_deps_dir_s = c_deps_dir.display();
 
-    // Check if we should build the perf_pt backend.
-    if cfg!(all(target_os = "linux", target_arch = "x86_64"))
+    // `cfg!` in a build script reflects the host running the script, not
+    // the crate's target, so cross-compiling needs the target triple that
+    // cargo passes through these env vars instead.
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap();
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH").unwrap();
+    let target = env::var("TARGET").unwrap();
+    let host = env::var("HOST").unwrap();
+
+    // Check if we should build the perf_pt backend. Each backend gets
+    // its own cfg (see `cargo:rustc-cfg=perf_pt` below) so the crate can
+    // register whichever subset of backends actually built into
+    // `src/backends::available_backends()`, rather than assuming exactly
+    // one is present.
+    if target_os == "linux"
+        && target_arch == "x86_64"
         && feature_check("check_perf_pt.c", "check_perf_pt")
     {
         c_build.file("src/backends/perf_pt/collect.c");
         c_build.file("src/backends/perf_pt/decode.c");
         c_build.file("src/backends/perf_pt/util.c");
 
-        build_libxdc(&c_deps_dir);
+        let (libxdc_dir, capstone_dir) = build_system::prepare::prepare(&c_deps_dir);
+        let inst_dir = c_deps_dir.join("inst");
+        fs::create_dir_all(&inst_dir).unwrap();
+        build_with_make(&capstone_dir, "capstone", &target, &host, &inst_dir);
+        build_with_make(&libxdc_dir, "libxdc", &target, &host, &inst_dir);
         c_build.include(&format!("{}/inst/include/", c_deps_dir_s));
         c_build.flag(&format!("-L{}/inst/lib", c_deps_dir_s));
         println!("cargo:rustc-link-search={}/inst/lib", c_deps_dir_s);
 
         println!("cargo:rustc-cfg=perf_pt");
-        if cpu_supports_pt() {
+        // Baking a compile-time CPUID probe into the binary is only valid
+        // when the build host and the execution host are the same machine.
+        if target == host && cpu_supports_pt() {
             println!("cargo:rustc-cfg=perf_pt_test");
         }
         println!("cargo:rustc-link-lib=static=xdc");
         println!("cargo:rustc-link-lib=static=capstone");
     }
+    c_build.target(&target);
     c_build.include("src/util");
     c_build.compile("hwtracer_c");
 
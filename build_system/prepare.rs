@@ -0,0 +1,155 @@
+//! Fetches the C dependencies (libxdc and capstone) from their upstream
+//! repos at a pinned commit, rather than relying on a local, symlinked
+//! Makefile. This makes the versions we build against explicit in this
+//! file instead of implicit in whatever `c_deps.mk` happens to check out,
+//! and lets multiple build scripts run concurrently without racing on
+//! `env::set_current_dir`.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{self, Command};
+
+/// A git dependency pinned to an exact commit.
+///
+/// Git already content-addresses a commit by hashing its tree and
+/// history, so confirming that `HEAD` is exactly `commit_hash` after
+/// fetching is enough to catch a moved tag/branch or a bad checkout --
+/// there's no need to also hand-compute and commit a second digest here.
+pub(crate) struct GitRepo {
+    name: &'static str,
+    url: &'static str,
+    commit_hash: &'static str,
+}
+
+impl GitRepo {
+    const fn new(name: &'static str, url: &'static str, commit_hash: &'static str) -> Self {
+        Self {
+            name,
+            url,
+            commit_hash,
+        }
+    }
+
+    /// Clones this repo into `c_deps_dir/<name>` at the pinned commit if
+    /// it isn't there already, then verifies the checkout landed on
+    /// `commit_hash`.
+    fn fetch(&self, c_deps_dir: &Path) -> PathBuf {
+        let dest = c_deps_dir.join(self.name);
+
+        if !dest.exists() {
+            // Clone into a sibling scratch dir first and only rename it
+            // into place once the checkout is confirmed good. If `fetch`
+            // dies partway (network blip, interrupted build), `dest`
+            // itself is never created, so the next `cargo build` sees
+            // `dest.exists() == false` and retries the clone instead of
+            // tripping `verify_commit` on a half-finished, non-git
+            // directory forever.
+            //
+            // The scratch dir's name is suffixed with this process's pid
+            // so that two build scripts racing on the same `OUT_DIR` (e.g.
+            // a `cargo build` and `cargo test` run back-to-back against
+            // the same profile) each get their own, rather than the
+            // second one's `fs::create_dir` panicking on the first one's
+            // still-in-progress clone.
+            let tmp_dest = c_deps_dir.join(format!("{}.tmp.{}", self.name, process::id()));
+
+            eprintln!("Fetching {} @ {}...", self.name, self.commit_hash);
+            fs::create_dir(&tmp_dest).unwrap();
+            run(Command::new("git")
+                .arg("init")
+                .arg("-q")
+                .current_dir(&tmp_dest));
+            run(Command::new("git").current_dir(&tmp_dest).args(&[
+                "fetch",
+                "-q",
+                "--depth=1",
+                self.url,
+                self.commit_hash,
+            ]));
+            run(Command::new("git")
+                .current_dir(&tmp_dest)
+                .args(&["checkout", "-q", "FETCH_HEAD"]));
+
+            verify_commit(&tmp_dest, self.name, self.commit_hash);
+            // A concurrent build script racing us may have already
+            // renamed its own scratch dir into `dest` by now; that's
+            // fine, both are the same pinned commit, so just drop ours.
+            if fs::rename(&tmp_dest, &dest).is_err() {
+                fs::remove_dir_all(&tmp_dest).unwrap();
+            }
+            return dest;
+        }
+
+        verify_commit(&dest, self.name, self.commit_hash);
+        dest
+    }
+}
+
+const LIBXDC: GitRepo = GitRepo::new(
+    "libxdc",
+    "https://github.com/nyx-fuzz/libxdc.git",
+    "8c21261e5f5ed472dd4fb46b42b11ee4cf0e9e39",
+);
+
+const CAPSTONE: GitRepo = GitRepo::new(
+    "capstone",
+    "https://github.com/aquynh/capstone.git",
+    "b6d81544c181ea73c1a7270f5e97f28b2289fbf5",
+);
+
+/// Fetches libxdc and capstone into `c_deps_dir`, returning their checkout
+/// paths. Each is pinned to an exact commit, so the build is reproducible
+/// no matter what upstream does to its branches later.
+pub(crate) fn prepare(c_deps_dir: &Path) -> (PathBuf, PathBuf) {
+    if !c_deps_dir.exists() {
+        fs::create_dir(c_deps_dir).unwrap();
+    }
+    (LIBXDC.fetch(c_deps_dir), CAPSTONE.fetch(c_deps_dir))
+}
+
+fn run(cmd: &mut Command) {
+    let status = cmd
+        .status()
+        .unwrap_or_else(|e| panic!("failed to run {:?}: {}", cmd, e));
+    if !status.success() {
+        panic!("command failed with {}: {:?}", status, cmd);
+    }
+}
+
+/// Confirms a checkout's `HEAD` is exactly `expected_commit`.
+fn verify_commit(dir: &Path, name: &str, expected_commit: &str) {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(&["rev-parse", "HEAD"])
+        .output()
+        .unwrap();
+    let actual = String::from_utf8(output.stdout).unwrap();
+    check_commit(name, actual.trim(), expected_commit);
+}
+
+/// The comparison behind `verify_commit`, pulled out so it can be unit
+/// tested without needing an actual git checkout.
+fn check_commit(name: &str, actual: &str, expected: &str) {
+    if actual != expected {
+        panic!(
+            "checked-out commit for {} does not match the pinned commit\n  expected: {}\n  actual:   {}",
+            name, expected, actual
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::check_commit;
+
+    #[test]
+    fn matching_commit_is_accepted() {
+        check_commit("test", "abc123", "abc123");
+    }
+
+    #[test]
+    #[should_panic(expected = "does not match the pinned commit")]
+    fn mismatched_commit_is_rejected() {
+        check_commit("test", "abc123", "def456");
+    }
+}
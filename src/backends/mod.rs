@@ -0,0 +1,92 @@
+//! Tracing backends and the runtime registry used to pick between them.
+//!
+//! Unlike `perf_pt_test` (a compile-time cfg for gating hardware-only
+//! tests), which backends are compiled into this binary is not something
+//! callers should have to know about ahead of time: a binary may be built
+//! with several backends, only some of which the CPU it eventually runs
+//! on can actually use. [`TracerBuilder`] is how callers pick one, either
+//! by name or by letting hwtracer choose the first supported one.
+
+#[cfg(perf_pt)]
+pub mod perf_pt;
+
+use crate::HWTracerError;
+
+/// A tracing backend: something that can collect a hardware trace and
+/// decode it into a sequence of blocks.
+pub trait Backend {
+    /// Starts collecting a trace.
+    fn collect(&self) -> Result<(), HWTracerError>;
+    /// Decodes a previously collected trace.
+    fn decode(&self) -> Result<(), HWTracerError>;
+}
+
+/// Identifies a tracing backend that was compiled into this binary.
+///
+/// `build.rs` emits one cfg per backend it managed to build (e.g.
+/// `perf_pt`), and this enum has one variant per such cfg, so a variant
+/// only exists here when the corresponding backend module was actually
+/// compiled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackendKind {
+    #[cfg(perf_pt)]
+    PerfPT,
+}
+
+impl BackendKind {
+    /// Returns `true` if the CPU this process is running on meets this
+    /// backend's hardware requirements.
+    pub(crate) fn is_supported(&self) -> bool {
+        match *self {
+            #[cfg(perf_pt)]
+            BackendKind::PerfPT => crate::pt_supported(),
+        }
+    }
+
+    pub(crate) fn new_tracer(&self) -> Result<Box<dyn Backend>, HWTracerError> {
+        match *self {
+            #[cfg(perf_pt)]
+            BackendKind::PerfPT => Ok(Box::new(perf_pt::Tracer::new()?)),
+        }
+    }
+}
+
+/// Returns the tracing backends compiled into this binary, in the order
+/// they are tried when none is explicitly requested.
+pub fn available_backends() -> &'static [BackendKind] {
+    &[
+        #[cfg(perf_pt)]
+        BackendKind::PerfPT,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn available_backends_matches_compiled_in_cfgs() {
+        let kinds = available_backends();
+        #[cfg(perf_pt)]
+        assert!(kinds.contains(&BackendKind::PerfPT));
+        #[cfg(not(perf_pt))]
+        assert!(kinds.is_empty());
+    }
+
+    // These don't need real PT hardware: whichever way `pt_supported()`
+    // answers, `is_supported`/`new_tracer` just need to agree with it.
+    #[cfg(perf_pt)]
+    #[test]
+    fn perf_pt_is_supported_matches_runtime_probe() {
+        assert_eq!(BackendKind::PerfPT.is_supported(), crate::pt_supported());
+    }
+
+    #[cfg(perf_pt)]
+    #[test]
+    fn perf_pt_new_tracer_matches_runtime_probe() {
+        assert_eq!(
+            BackendKind::PerfPT.new_tracer().is_ok(),
+            crate::pt_supported()
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! The perf_pt backend: Intel PT tracing via Linux's perf event interface,
+//! with decoding by libxdc. The C side lives in `collect.c`/`decode.c`;
+//! the Rust [`Backend`] methods here do not call into it and return
+//! [`HWTracerError::Unimplemented`].
+
+use crate::backends::Backend;
+use crate::{pt_supported, HWTracerError};
+
+/// The perf_pt tracer. Construction checks for Intel PT support;
+/// [`Backend::collect`] and [`Backend::decode`] return
+/// [`HWTracerError::Unimplemented`].
+pub struct Tracer {
+    // Holds no state; private so that a `Tracer` can only be made via `new`.
+    _private: (),
+}
+
+impl Tracer {
+    /// Constructs a new perf_pt `Tracer`.
+    ///
+    /// Returns [`HWTracerError::NoPT`] if the CPU this process is running
+    /// on doesn't support Intel Processor Trace (see
+    /// [`crate::pt_supported`]).
+    pub fn new() -> Result<Self, HWTracerError> {
+        if !pt_supported() {
+            return Err(HWTracerError::NoPT);
+        }
+        Ok(Self { _private: () })
+    }
+}
+
+impl Backend for Tracer {
+    fn collect(&self) -> Result<(), HWTracerError> {
+        // TODO: wire this up to collect.c, which uses perf's Intel PT
+        // event to record a trace.
+        Err(HWTracerError::Unimplemented)
+    }
+
+    fn decode(&self) -> Result<(), HWTracerError> {
+        // TODO: wire this up to decode.c, which walks the recorded trace
+        // with libxdc.
+        Err(HWTracerError::Unimplemented)
+    }
+}
+
+#[cfg(all(test, perf_pt_test))]
+mod tests {
+    use super::Tracer;
+
+    // `perf_pt_test` is only set by build.rs when the build host has
+    // Intel PT, so this test only runs on hardware that can exercise it.
+    #[test]
+    fn new_tracer_on_pt_hardware() {
+        assert!(Tracer::new().is_ok());
+    }
+}
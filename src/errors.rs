@@ -0,0 +1,32 @@
+use std::error::Error;
+use std::fmt;
+
+/// Errors that can occur when collecting or decoding a hardware trace.
+#[derive(Debug)]
+pub enum HWTracerError {
+    /// The CPU this process is running on doesn't support Intel Processor
+    /// Trace. See [`crate::pt_supported`] for how this is determined.
+    NoPT,
+    /// This backend doesn't implement the requested operation yet.
+    Unimplemented,
+    /// None of the backends compiled into this binary could be used,
+    /// either because none were compiled in at all, or because the CPU
+    /// doesn't meet any of their hardware requirements.
+    NoBackend,
+}
+
+impl fmt::Display for HWTracerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HWTracerError::NoPT => write!(f, "CPU does not support Intel Processor Trace"),
+            HWTracerError::Unimplemented => {
+                write!(f, "operation not implemented for this backend")
+            }
+            HWTracerError::NoBackend => {
+                write!(f, "no usable tracing backend is compiled into this binary")
+            }
+        }
+    }
+}
+
+impl Error for HWTracerError {}
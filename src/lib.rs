@@ -0,0 +1,89 @@
+//! hwtracer collects and decodes hardware traces.
+
+pub mod backends;
+mod errors;
+
+pub use crate::errors::HWTracerError;
+
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::__cpuid_count;
+
+/// Returns `true` if the CPU this process is running on supports Intel
+/// Processor Trace.
+///
+/// This probes the actual execution host at runtime, so it gives a
+/// correct answer even in a binary that was built on different hardware
+/// (e.g. a CI runner or a prebuilt package), unlike a check baked in at
+/// compile time. Intel PT only exists on x86_64, so this is always
+/// `false` on other architectures.
+#[cfg(target_arch = "x86_64")]
+pub fn pt_supported() -> bool {
+    let res = unsafe { __cpuid_count(0x7, 0x0) };
+    (res.ebx & (1 << 25)) != 0
+}
+
+/// See the `x86_64` version of this function.
+#[cfg(not(target_arch = "x86_64"))]
+pub fn pt_supported() -> bool {
+    false
+}
+
+/// Builds a tracer, selecting among the backends compiled into this
+/// binary.
+///
+/// By default, [`build`](TracerBuilder::build) picks the first compiled-in
+/// backend whose hardware requirements the CPU satisfies. Call
+/// [`backend`](TracerBuilder::backend) first to request a specific one
+/// instead.
+#[derive(Default)]
+pub struct TracerBuilder {
+    backend: Option<backends::BackendKind>,
+}
+
+impl TracerBuilder {
+    /// Creates a new builder with no backend preference.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests a specific backend, rather than auto-selecting one.
+    pub fn backend(mut self, backend: backends::BackendKind) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Builds the tracer.
+    ///
+    /// Returns [`HWTracerError::NoBackend`] if an explicit backend wasn't
+    /// requested and no compiled-in backend is usable on this CPU, or
+    /// whatever error the requested backend's constructor returns (e.g.
+    /// [`HWTracerError::NoPT`]) if one was requested explicitly.
+    pub fn build(self) -> Result<Box<dyn backends::Backend>, HWTracerError> {
+        if let Some(backend) = self.backend {
+            return backend.new_tracer();
+        }
+
+        backends::available_backends()
+            .iter()
+            .find(|backend| backend.is_supported())
+            .ok_or(HWTracerError::NoBackend)?
+            .new_tracer()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // No backend cfg is compiled in on a non-Linux/x86_64 build (or one
+    // where the perf_pt feature check simply failed), so `build()` must
+    // fall back to `NoBackend` without needing any hardware.
+    #[cfg(not(perf_pt))]
+    #[test]
+    fn build_with_no_backends_returns_no_backend() {
+        assert!(matches!(
+            TracerBuilder::new().build(),
+            Err(HWTracerError::NoBackend)
+        ));
+    }
+}